@@ -1,117 +1,239 @@
 //! An implementation of a `ByteBuf` based on virtual memory.
 //!
-//! This implementation uses `mmap` on POSIX systems (and should use `VirtualAlloc` on windows).
+//! This implementation uses `mmap` on POSIX systems and `VirtualAlloc` on Windows.
 //! There are possibilities to improve the performance for the reallocating case by reserving
 //! memory up to maximum. This might be a problem for systems that don't have a lot of virtual
 //! memory (i.e. 32-bit platforms).
 
-use std::ptr::{self, NonNull};
-use std::slice;
+use std::ptr::NonNull;
 use super::{MemoryBackend, ByteBuf};
 
-struct Mmap {
-    /// The pointer that points to the start of the mapping.
-    ///
-    /// This value doesn't change after creation.
-    ptr: NonNull<u8>,
-    /// The length of this mapping.
-    ///
-    /// Cannot be more than `isize::max_value()`. This value doesn't change after creation.
-    len: usize,
-}
+#[cfg(unix)]
+mod sys {
+    use std::ptr::{self, NonNull};
+    use std::slice;
 
-impl Mmap {
-    /// Create a new mmap mapping
-    ///
-    /// Returns `Err` if:
-    /// - `len` should not exceed `isize::max_value()`
-    /// - `len` should be greater than 0.
-    /// - `mmap` returns an error (almost certainly means out of memory).
-    fn new(len: usize) -> Result<Self, &'static str> {
-        if len > isize::max_value() as usize {
-            return Err("`len` should not exceed `isize::max_value()`");
-        }
-        if len == 0 {
-            return Err("`len` should be greater than 0");
+    pub struct Mmap {
+        /// The pointer that points to the start of the mapping.
+        ///
+        /// This value doesn't change after creation.
+        ptr: NonNull<u8>,
+        /// The length of this mapping.
+        ///
+        /// Cannot be more than `isize::max_value()`. This value doesn't change after creation.
+        len: usize,
+    }
+
+    impl Mmap {
+        /// Create a new mmap mapping
+        ///
+        /// Returns `Err` if:
+        /// - `len` should not exceed `isize::max_value()`
+        /// - `len` should be greater than 0.
+        /// - `mmap` returns an error (almost certainly means out of memory).
+        pub fn new(len: usize) -> Result<Self, &'static str> {
+            if len > isize::max_value() as usize {
+                return Err("`len` should not exceed `isize::max_value()`");
+            }
+            if len == 0 {
+                return Err("`len` should be greater than 0");
+            }
+
+            let ptr_or_err = unsafe {
+                // Safety Proof:
+                // There are not specific safety proofs are required for this call, since the call
+                // by itself can't invoke any safety problems (however, misusing its result can).
+                libc::mmap(
+                    // `addr` - let the system to choose the address at which to create the mapping.
+                    ptr::null_mut(),
+                    // the length of the mapping in bytes.
+                    len,
+                    // `prot` - protection flags: READ WRITE !EXECUTE
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    // `flags`
+                    // `MAP_ANON` - mapping is not backed by any file and initial contents are
+                    // initialized to zero.
+                    // `MAP_PRIVATE` - the mapping is private to this process.
+                    libc::MAP_ANON | libc::MAP_PRIVATE,
+                    // `fildes` - a file descriptor. Pass -1 as this is required for some platforms
+                    // when the `MAP_ANON` is passed.
+                    -1,
+                    // `offset` - offset from the file.
+                    0,
+                )
+            };
+
+            match ptr_or_err {
+                // With the current parameters, the error can only be returned in case of insufficient
+                // memory.
+                libc::MAP_FAILED => Err("mmap returned an error"),
+                _ => {
+                    let ptr = NonNull::new(ptr_or_err as *mut u8).ok_or("mmap returned 0")?;
+                    Ok(Self { ptr, len })
+                }
+            }
         }
 
-        let ptr_or_err = unsafe {
-            // Safety Proof:
-            // There are not specific safety proofs are required for this call, since the call
-            // by itself can't invoke any safety problems (however, misusing its result can).
-            libc::mmap(
-                // `addr` - let the system to choose the address at which to create the mapping.
-                ptr::null_mut(),
-                // the length of the mapping in bytes.
-                len,
-                // `prot` - protection flags: READ WRITE !EXECUTE
-                libc::PROT_READ | libc::PROT_WRITE,
-                // `flags`
-                // `MAP_ANON` - mapping is not backed by any file and initial contents are
-                // initialized to zero.
-                // `MAP_PRIVATE` - the mapping is private to this process.
-                libc::MAP_ANON | libc::MAP_PRIVATE,
-                // `fildes` - a file descriptor. Pass -1 as this is required for some platforms
-                // when the `MAP_ANON` is passed.
-                -1,
-                // `offset` - offset from the file.
-                0,
-            )
-        };
+        pub fn as_slice(&self) -> &[u8] {
+            unsafe {
+                // Safety Proof:
+                // - Aliasing guarantees of `self.ptr` are not violated since `self` is the only owner.
+                // - This pointer was allocated for `self.len` bytes and thus is a valid slice.
+                // - `self.len` doesn't change throughout the lifetime of `self`.
+                // - The value is returned valid for the duration of lifetime of `self`.
+                //   `self` cannot be destroyed while the returned slice is alive.
+                // - `self.ptr` is of `NonNull` type and thus `.as_ptr()` can never return NULL.
+                // - `self.len` cannot be larger than `isize::max_value()`.
+                slice::from_raw_parts(self.ptr.as_ptr(), self.len)
+            }
+        }
 
-        match ptr_or_err {
-            // With the current parameters, the error can only be returned in case of insufficient
-            // memory.
-            libc::MAP_FAILED => Err("mmap returned an error"),
-            _ => {
-                let ptr = NonNull::new(ptr_or_err as *mut u8).ok_or("mmap returned 0")?;
-                Ok(Self { ptr, len })
+        pub fn as_slice_mut(&mut self) -> &mut [u8] {
+            unsafe {
+                // Safety Proof:
+                // - See the proof for `Self::as_slice`
+                // - Additionally, it is not possible to obtain two mutable references for `self.ptr`
+                slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len)
             }
         }
-    }
 
-    fn as_slice(&self) -> &[u8] {
-        unsafe {
-            // Safety Proof:
-            // - Aliasing guarantees of `self.ptr` are not violated since `self` is the only owner.
-            // - This pointer was allocated for `self.len` bytes and thus is a valid slice.
-            // - `self.len` doesn't change throughout the lifetime of `self`.
-            // - The value is returned valid for the duration of lifetime of `self`.
-            //   `self` cannot be destroyed while the returned slice is alive.
-            // - `self.ptr` is of `NonNull` type and thus `.as_ptr()` can never return NULL.
-            // - `self.len` cannot be larger than `isize::max_value()`.
-            slice::from_raw_parts(self.ptr.as_ptr(), self.len)
+        pub fn ptr(&self) -> *mut u8 {
+            self.ptr.as_ptr()
+        }
+
+        pub fn len(&self) -> usize {
+            self.len
         }
     }
 
-    fn as_slice_mut(&mut self) -> &mut [u8] {
-        unsafe {
-            // Safety Proof:
-            // - See the proof for `Self::as_slice`
-            // - Additionally, it is not possible to obtain two mutable references for `self.ptr`
-            slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len)
+    impl Drop for Mmap {
+        fn drop(&mut self) {
+            let ret_val = unsafe {
+                // Safety proof:
+                // - `self.ptr` was allocated by a call to `mmap`.
+                // - `self.len` was saved at the same time and it doesn't change throughout the lifetime
+                //   of `self`.
+                libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.len)
+            };
+
+            // There is no reason for `munmap` to fail to deallocate a private annonymous mapping
+            // allocated by `mmap`.
+            // However, for the cases when it actually fails prefer to fail, in order to not leak
+            // and exhaust the virtual memory.
+            assert_eq!(ret_val, 0, "munmap failed");
         }
     }
 }
 
-impl Drop for Mmap {
-    fn drop(&mut self) {
-        let ret_val = unsafe {
-            // Safety proof:
-            // - `self.ptr` was allocated by a call to `mmap`.
-            // - `self.len` was saved at the same time and it doesn't change throughout the lifetime
-            //   of `self`.
-            libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.len)
-        };
+#[cfg(windows)]
+mod sys {
+    use std::ptr::{self, NonNull};
+    use std::slice;
+    use winapi::um::memoryapi::{VirtualAlloc, VirtualFree};
+    use winapi::um::winnt::{MEM_COMMIT, MEM_RELEASE, MEM_RESERVE, PAGE_READWRITE};
 
-        // There is no reason for `munmap` to fail to deallocate a private annonymous mapping
-        // allocated by `mmap`.
-        // However, for the cases when it actually fails prefer to fail, in order to not leak
-        // and exhaust the virtual memory.
-        assert_eq!(ret_val, 0, "munmap failed");
+    pub struct Mmap {
+        /// The pointer that points to the start of the reservation.
+        ///
+        /// This value doesn't change after creation.
+        ptr: NonNull<u8>,
+        /// The length of this reservation.
+        ///
+        /// Cannot be more than `isize::max_value()`. This value doesn't change after creation.
+        len: usize,
+    }
+
+    impl Mmap {
+        /// Create a new mapping, reserving and committing `len` bytes in one go.
+        ///
+        /// Returns `Err` if:
+        /// - `len` should not exceed `isize::max_value()`
+        /// - `len` should be greater than 0.
+        /// - `VirtualAlloc` returns an error (almost certainly means out of memory).
+        pub fn new(len: usize) -> Result<Self, &'static str> {
+            if len > isize::max_value() as usize {
+                return Err("`len` should not exceed `isize::max_value()`");
+            }
+            if len == 0 {
+                return Err("`len` should be greater than 0");
+            }
+
+            let ptr_or_err = unsafe {
+                // Safety Proof:
+                // There are not specific safety proofs are required for this call, since the call
+                // by itself can't invoke any safety problems (however, misusing its result can).
+                VirtualAlloc(
+                    // `lpAddress` - let the system choose the address at which to reserve the region.
+                    ptr::null_mut(),
+                    // the length of the region in bytes.
+                    len,
+                    // `flAllocationType` - reserve and commit the region in one call.
+                    MEM_RESERVE | MEM_COMMIT,
+                    // `flProtect` - protection flags: READ WRITE !EXECUTE
+                    PAGE_READWRITE,
+                )
+            };
+
+            if ptr_or_err.is_null() {
+                return Err("VirtualAlloc returned an error");
+            }
+
+            let ptr = NonNull::new(ptr_or_err as *mut u8).ok_or("VirtualAlloc returned 0")?;
+            Ok(Self { ptr, len })
+        }
+
+        pub fn as_slice(&self) -> &[u8] {
+            unsafe {
+                // Safety Proof:
+                // - Aliasing guarantees of `self.ptr` are not violated since `self` is the only owner.
+                // - This pointer was allocated for `self.len` bytes and thus is a valid slice.
+                // - `self.len` doesn't change throughout the lifetime of `self`.
+                // - The value is returned valid for the duration of lifetime of `self`.
+                //   `self` cannot be destroyed while the returned slice is alive.
+                // - `self.ptr` is of `NonNull` type and thus `.as_ptr()` can never return NULL.
+                // - `self.len` cannot be larger than `isize::max_value()`.
+                slice::from_raw_parts(self.ptr.as_ptr(), self.len)
+            }
+        }
+
+        pub fn as_slice_mut(&mut self) -> &mut [u8] {
+            unsafe {
+                // Safety Proof:
+                // - See the proof for `Self::as_slice`
+                // - Additionally, it is not possible to obtain two mutable references for `self.ptr`
+                slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len)
+            }
+        }
+
+        pub fn ptr(&self) -> *mut u8 {
+            self.ptr.as_ptr()
+        }
+
+        pub fn len(&self) -> usize {
+            self.len
+        }
+    }
+
+    impl Drop for Mmap {
+        fn drop(&mut self) {
+            let ret_val = unsafe {
+                // Safety proof:
+                // - `self.ptr` was allocated by a call to `VirtualAlloc` with `MEM_RESERVE | MEM_COMMIT`.
+                // - `MEM_RELEASE` requires the base address returned by the reserving call and a
+                //   `dwSize` of 0, which releases the entire region in one call.
+                VirtualFree(self.ptr.as_ptr() as *mut _, 0, MEM_RELEASE)
+            };
+
+            // There is no reason for `VirtualFree` to fail to release a region reserved by
+            // `VirtualAlloc`. However, for the cases when it actually fails prefer to fail, in
+            // order to not leak and exhaust the virtual memory.
+            assert_ne!(ret_val, 0, "VirtualFree failed");
+        }
     }
 }
 
+use self::sys::Mmap;
+
 pub struct MmapByteBuf {
     mmap: Option<Mmap>,
 }
@@ -151,15 +273,15 @@ impl MemoryBackend for MmapByteBuf {
         };
 
         let bytebuf = ByteBuf {
-            ptr: new_mmap.as_ref().map(|m| m.ptr.as_ptr()).unwrap_or(NonNull::dangling().as_ptr()),
-            len: new_mmap.as_ref().map(|m| m.len).unwrap_or(0),
+            ptr: new_mmap.as_ref().map(|m| m.ptr()).unwrap_or(NonNull::dangling().as_ptr()),
+            len: new_mmap.as_ref().map(|m| m.len()).unwrap_or(0),
         };
         self.mmap = new_mmap;
         Ok(bytebuf)
     }
 
     fn erase(&mut self) -> Result<(), &'static str> {
-        let len = self.mmap.as_ref().map(|m| m.len).unwrap_or(0);
+        let len = self.mmap.as_ref().map(|m| m.len()).unwrap_or(0);
         if len > 0 {
             // The order is important.
             //